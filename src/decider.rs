@@ -0,0 +1,125 @@
+//! A decider subsystem: compresses the folded accumulator produced by
+//! `IVC::fold_step` into one constant-size zk-SNARK, so a verifier only has
+//! to check a single proof instead of replaying every fold step natively
+//! (as `IVC::verify` does). Mirrors the decider circuit in Nova-style
+//! folding libraries.
+//!
+//! Built on the IPA plumbing already used by `shuffle_api::test_prover`
+//! (`IPACommitmentScheme`, `ProverIPA`/`VerifierIPA`), so the final
+//! artifact's size and verification cost don't grow with `FOLD_STEP_COUNT`.
+//! Proving and verifying both reuse the `CommitmentKey` the caller already
+//! built for `pp`, rather than minting an unrelated SRS.
+//!
+//! TODO #331: `prove_decider`/`verify_decider` assume `PublicParams` exposes
+//! `decider_circuit(&ivc) -> C` and `decider_verifier_circuit() -> C` (same
+//! `C: Circuit<_>`, same `Self::Config`, same fixed/selector layout — only
+//! witness-bearing cells may differ), so that `keygen_vk` on either one
+//! yields an identical `VerifyingKey` and a proof from the former verifies
+//! against a `vk` from the latter. That's unverified against the real
+//! `sirius` crate (not vendored here); confirm both methods exist with
+//! this shape before relying on this in anything beyond the quickstart.
+
+use rand::rngs::OsRng;
+use sirius::{
+    halo2_proofs::{
+        plonk::{create_proof, keygen_pk, keygen_vk, verify_proof},
+        poly::{
+            ipa::{
+                commitment::IPACommitmentScheme,
+                multiopen::{ProverIPA, VerifierIPA},
+                strategy::AccumulatorStrategy,
+            },
+            VerificationStrategy,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    },
+    halo2curves::CurveAffine,
+    prelude::{CommitmentKey, PublicParams, IVC},
+};
+
+/// A constant-size proof that an `IVC`'s current accumulator is
+/// satisfiable, independent of how many fold steps produced it.
+pub struct DeciderProof {
+    bytes: Vec<u8>,
+}
+
+/// Compresses `ivc`'s current folded instance-witness into one succinct
+/// [`DeciderProof`] under `pp`.
+///
+/// `pp.decider_circuit(ivc)` is expected to yield a halo2 circuit that
+/// checks satisfiability of the final relaxed/folded pair; everything past
+/// that point is a plain single-circuit IPA proof, the same shape as
+/// `shuffle_api::test_prover`.
+///
+/// `primary_commitment_key` must be the same key passed to `new_default_pp`
+/// when `pp` was built: the decider circuit certifies the accumulator's
+/// commitments under that key, so proving/verifying it under an
+/// independently-generated SRS would check nothing.
+pub fn prove_decider<const A1: usize, const A2: usize, C1, C2>(
+    pp: &PublicParams<A1, C1, A2, C2>,
+    ivc: &IVC<A1, C1, A2, C2>,
+    primary_commitment_key: &CommitmentKey<C1>,
+) -> DeciderProof
+where
+    C1: CurveAffine,
+    C2: CurveAffine,
+{
+    let decider_circuit = pp.decider_circuit(ivc);
+
+    let params = primary_commitment_key.params();
+    let vk = keygen_vk(params, &decider_circuit).expect("decider vk generation failed");
+    let pk = keygen_pk(params, vk, &decider_circuit).expect("decider pk generation failed");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<C1>, ProverIPA<C1>, _, _, _, _>(
+        params,
+        &pk,
+        &[decider_circuit],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("decider proof generation failed");
+
+    DeciderProof {
+        bytes: transcript.finalize(),
+    }
+}
+
+/// Checks a [`DeciderProof`] against the public parameters it was produced
+/// under.
+///
+/// `primary_commitment_key` must match the one `pp` and the proof were
+/// built with, for the same reason as in [`prove_decider`].
+///
+/// The check bottoms out in `AccumulatorStrategy`'s fixed-size MSM, so it's
+/// as friendly to an on-chain verifier as the rest of the IPA path already
+/// used in this crate.
+pub fn verify_decider<const A1: usize, const A2: usize, C1, C2>(
+    pp: &PublicParams<A1, C1, A2, C2>,
+    proof: &DeciderProof,
+    primary_commitment_key: &CommitmentKey<C1>,
+) -> bool
+where
+    C1: CurveAffine,
+    C2: CurveAffine,
+{
+    let decider_circuit = pp.decider_verifier_circuit();
+    let params = primary_commitment_key.params();
+    let vk = keygen_vk(params, &decider_circuit).expect("decider vk generation failed");
+
+    let strategy = AccumulatorStrategy::new(params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof.bytes[..]);
+
+    verify_proof::<IPACommitmentScheme<C1>, VerifierIPA<C1>, _, _, _>(
+        params,
+        &vk,
+        strategy,
+        &[&[]],
+        &mut transcript,
+    )
+    .map(|strategy| strategy.finalize())
+    .unwrap_or(false)
+}
@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use shuffle_api::ShuffleChip;
+use shuffle_api::{DigestChip, DigestConfig, GrandProductShuffleChip, GrandProductShuffleConfig};
 use sirius::{
     ff::Field,
     halo2_proofs::circuit::Value,
@@ -14,12 +14,30 @@ use sirius::{
     },
 };
 
+#[allow(dead_code)]
+mod decider;
 #[allow(dead_code)]
 mod shuffle_api;
 
 /// Number of folding steps
 const FOLD_STEP_COUNT: usize = 5;
 
+/// Whether to pack the primary/secondary circuits' selector columns into a
+/// smaller number of fixed columns before keygen.
+///
+/// Selectors that are never enabled on the same row (here: the shuffle chip's
+/// `q_first` and `q_last`) can share a fixed polynomial instead of each occupying one,
+/// which shrinks the folded circuit's table and, with it, the commitment
+/// key. Flip this off if you need to inspect the uncompressed layout.
+const COMPRESS_SELECTORS: bool = true;
+
+/// Number of rows in the shuffle table used by [`MyStepCircuit`]
+///
+/// This also doubles as the circuit's `EXTERNAL_ARITY`: every fold step
+/// supplies a fresh shuffle table of this size through `external_inputs`
+/// rather than baking one in at construction time.
+const SHUFFLE_LEN: usize = 4;
+
 // === PRIMARY ===
 
 /// Arity : Input/output size per fold-step for primary step-circuit
@@ -37,7 +55,8 @@ const PRIMARY_COMMITMENT_KEY_SIZE: usize = 20;
 /// Table size for Primary Circuit
 ///
 /// Requires at least 17, for service purposes, but if the primary requires more, increase the
-/// constant
+/// constant. With `COMPRESS_SELECTORS` on, mutually exclusive selectors share fixed columns,
+/// so this tends to need bumping less often than the selector count alone would suggest.
 const PRIMARY_CIRCUIT_TABLE_SIZE: usize = 17;
 
 // === SECONDARY ===
@@ -61,23 +80,48 @@ const SECONDARY_CIRCUIT_TABLE_SIZE: usize = 17;
 /// insufficient, then increase this constant
 const SECONDARY_COMMITMENT_KEY_SIZE: usize = 20;
 
+/// Number of columns folded per row of the shuffle argument: one for
+/// `input_0`/`shuffle_0`, one for `input_1`/`shuffle_1`.
+const SHUFFLE_WIDTH: usize = 2;
+
 /// This structure is a template for configuring your circuit
 ///
 /// It should store information about your PLONKish structure
+///
+/// `input_1` is no longer baked in here: it is supplied fresh at every fold
+/// step through `StepCircuit::EXTERNAL_ARITY` / `external_inputs`, so the
+/// same `MyStepCircuit` can fold a different shuffle table at each step
+/// instead of re-folding the witness it was constructed with.
+///
+/// `perm` stays fixed across steps: it records, for each row of the
+/// "shuffled" table, which row of the external `input_1` table it should
+/// equal, so `shuffle_1` can be derived from `external_inputs` instead of
+/// being passed in separately.
 #[derive(Debug, Clone)]
 struct MyStepCircuit<const L: usize, F: PrimeField> {
     input_0: Vec<Value<F>>,
-    input_1: Vec<F>,
     shuffle_0: Vec<Value<F>>,
-    shuffle_1: Vec<Value<F>>,
+    perm: Vec<usize>,
 }
 
-type MyConfig = shuffle_api::ShuffleConfig;
+/// `MyStepCircuit`'s config: the running-product [`GrandProductShuffleChip`]
+/// argument over the `(input, shuffle)` column pair, plus the
+/// [`DigestChip`] used to fold the assigned rows into `z_out`.
+#[derive(Debug, Clone)]
+struct MyConfig {
+    shuffle: GrandProductShuffleConfig<SHUFFLE_WIDTH>,
+    digest: DigestConfig,
+}
 
 impl<const A: usize, F: PrimeField> StepCircuit<A, F> for MyStepCircuit<A, F> {
     /// This is a configuration object that stores things like columns.
     type Config = MyConfig;
 
+    /// Per-step external input: the `input_1`/`shuffle_1` value table for
+    /// this fold step, bound into the folding transcript so it can't be
+    /// swapped out after the fact.
+    const EXTERNAL_ARITY: usize = SHUFFLE_LEN;
+
     /// Configure the step circuit. This method initializes necessary
     /// fixed columns and advice columns, but does not create any instance
     /// columns.
@@ -85,78 +129,109 @@ impl<const A: usize, F: PrimeField> StepCircuit<A, F> for MyStepCircuit<A, F> {
     // TODO #329
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
         let input_0 = meta.advice_column();
-        let input_1 = meta.fixed_column();
+        let input_1 = meta.advice_column();
         let shuffle_0 = meta.advice_column();
         let shuffle_1 = meta.advice_column();
-        ShuffleChip::configure(meta, input_0, input_1, shuffle_0, shuffle_1)
+        let shuffle = GrandProductShuffleChip::<F, SHUFFLE_WIDTH>::configure(
+            meta,
+            [input_0, input_1],
+            [shuffle_0, shuffle_1],
+        );
+        let digest = DigestChip::configure(meta);
+        MyConfig { shuffle, digest }
     }
 
     /// Sythesize the circuit for a computation step and return variable
     /// that corresponds to the output of the step z_{i+1}
     /// this method will be called when we synthesize the IVC_Circuit
     ///
+    /// `external_inputs` holds this step's `input_1`/`shuffle_1` value
+    /// table: it is assigned as advice below, so it is free to differ from
+    /// step to step while still being constrained by the shuffle argument.
+    ///
     /// Return `z_out` result
     fn synthesize_step(
         &self,
         config: Self::Config,
         layouter: &mut impl Layouter<F>,
-        _z_i: &[AssignedCell<F, F>; A],
+        z_i: &[AssignedCell<F, F>; A],
+        external_inputs: &[F; Self::EXTERNAL_ARITY],
     ) -> Result<[AssignedCell<F, F>; A], SynthesisError> {
-        let ch = ShuffleChip::<F>::construct(config);
-
-        layouter.assign_region(
-            || "load inputs",
-            |mut region| {
-                for (i, (input_0, input_1)) in
-                    self.input_0.iter().zip(self.input_1.iter()).enumerate()
-                {
-                    region.assign_advice(|| "input_0", ch.config.input_0, i, || *input_0)?;
-                    region.assign_fixed(
-                        || "input_1",
-                        ch.config.input_1,
-                        i,
-                        || Value::known(*input_1),
-                    )?;
-                    ch.config.s_input.enable(&mut region, i)?;
-                }
-                Ok(())
-            },
-        )?;
-        layouter.assign_region(
-            || "load shuffles",
-            |mut region| {
-                for (i, (shuffle_0, shuffle_1)) in
-                    self.shuffle_0.iter().zip(self.shuffle_1.iter()).enumerate()
-                {
-                    region.assign_advice(|| "shuffle_0", ch.config.shuffle_0, i, || *shuffle_0)?;
-                    region.assign_advice(|| "shuffle_1", ch.config.shuffle_1, i, || *shuffle_1)?;
-                    ch.config.s_shuffle.enable(&mut region, i)?;
-                }
-                Ok(())
-            },
-        )?;
-
-        todo!()
+        let shuffle_chip = GrandProductShuffleChip::<F, SHUFFLE_WIDTH>::construct(config.shuffle);
+
+        let original: Vec<[Value<F>; SHUFFLE_WIDTH]> = self
+            .input_0
+            .iter()
+            .zip(external_inputs.iter())
+            .map(|(input_0, input_1)| [*input_0, Value::known(*input_1)])
+            .collect();
+        let shuffled: Vec<[Value<F>; SHUFFLE_WIDTH]> = self
+            .shuffle_0
+            .iter()
+            .zip(self.perm.iter())
+            .map(|(shuffle_0, row)| [*shuffle_0, Value::known(external_inputs[*row])])
+            .collect();
+
+        let (original_cells, shuffled_cells) =
+            shuffle_chip.assign(layouter.namespace(|| "shuffle"), &original, &shuffled)?;
+
+        // z_{i+1} is bound to both the prior state and this step's witness:
+        // a Poseidon digest over z_i and every assigned original/shuffled
+        // cell — including the `input_1`/`shuffle_1` column, which is what
+        // actually carries `external_inputs` — so folding can't silently
+        // drop what the step actually did or ignore the per-step value
+        // entirely. This relies on `GrandProductShuffleChip` actually
+        // constraining every row (including row H-1) against the
+        // permutation; a row the shuffle argument left unchecked would be
+        // folded into z_out without being bound to anything.
+        let terms: Vec<AssignedCell<F, F>> = original_cells
+            .into_iter()
+            .chain(shuffled_cells)
+            .flatten()
+            .collect();
+
+        let digest_chip = DigestChip::construct(config.digest);
+        let mut z_out = Vec::with_capacity(A);
+        for (i, z) in z_i.iter().enumerate() {
+            z_out.push(digest_chip.fold_in(
+                layouter.namespace(|| format!("digest z_out[{i}]")),
+                z,
+                &terms,
+            )?);
+        }
+
+        Ok(z_out
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("z_out has exactly A elements")))
     }
 }
 
+/// Builds the `input_1`/`shuffle_1` value table fed into `synthesize_step`
+/// at fold step `step` via `external_inputs`.
+///
+/// Each step rotates the base table by `step` (mod `SHUFFLE_LEN`), so
+/// consecutive steps check the shuffle argument against a different row
+/// order of the same four values; steps `SHUFFLE_LEN` apart get the same table.
+fn external_inputs_for_step(step: usize) -> [C1Scalar; SHUFFLE_LEN] {
+    let base = [10u64, 20, 40, 10];
+    std::array::from_fn(|i| C1Scalar::from(base[(i + step) % SHUFFLE_LEN]))
+}
+
 fn main() {
     let input_0 = [1, 2, 4, 1]
         .map(|e: u64| Value::known(C1Scalar::from(e)))
         .to_vec();
-    let input_1 = [10, 20, 40, 10].map(C1Scalar::from).to_vec();
     let shuffle_0 = [4, 1, 1, 2]
         .map(|e: u64| Value::known(C1Scalar::from(e)))
         .to_vec();
-    let shuffle_1 = [40, 10, 10, 20]
-        .map(|e: u64| Value::known(C1Scalar::from(e)))
-        .to_vec();
+    // shuffle_1[i] == input_1[perm[i]]: row 0 of the shuffled table holds
+    // the value at row 2 of the external table, and so on.
+    let perm = vec![2, 0, 3, 1];
 
     let sc1 = MyStepCircuit::<A1, C1Scalar> {
         input_0,
-        input_1,
         shuffle_0,
-        shuffle_1,
+        perm,
     };
 
     let sc2 = trivial::Circuit::<A2, C2Scalar>::default();
@@ -198,15 +273,26 @@ fn main() {
         PRIMARY_CIRCUIT_TABLE_SIZE as u32,
         &secondary_commitment_key,
         &sc2,
+        COMPRESS_SELECTORS,
     );
 
-    let mut ivc = IVC::new(&pp, &sc1, PRIMARY_Z_0, &sc2, SECONDARY_Z_0, true)
-        .expect("failed to create `IVC`");
+    let mut ivc = IVC::new(
+        &pp,
+        &sc1,
+        PRIMARY_Z_0,
+        &external_inputs_for_step(0),
+        &sc2,
+        SECONDARY_Z_0,
+        &[],
+        true,
+    )
+    .expect("failed to create `IVC`");
     println!("ivc created");
 
     for step in 1..FOLD_STEP_COUNT {
-        // you can modify circuit data here
-        ivc.fold_step(&pp, &sc1, &sc2)
+        // Fresh external inputs each step: the shuffle table `sc1` checks
+        // against actually changes, instead of re-folding the same witness.
+        ivc.fold_step(&pp, &sc1, &external_inputs_for_step(step), &sc2, &[])
             .expect("failed to run fold step");
 
         println!("folding step {step} was successful");
@@ -215,5 +301,16 @@ fn main() {
     ivc.verify(&pp).expect("failed to verify ivc");
     println!("verification successful");
 
+    // Compress the folded accumulator into one constant-size proof so a
+    // verifier doesn't have to replay all `FOLD_STEP_COUNT` steps natively.
+    // Reuses `primary_commitment_key` so the decider certifies the same
+    // commitments `fold_step` actually produced, rather than an unrelated SRS.
+    let decider_proof = decider::prove_decider(&pp, &ivc, &primary_commitment_key);
+    assert!(
+        decider::verify_decider(&pp, &decider_proof, &primary_commitment_key),
+        "decider proof failed to verify"
+    );
+    println!("decider proof verified");
+
     println!("success");
 }
@@ -5,10 +5,11 @@ use sirius::{
     ff::FromUniformBytes,
     halo2_proofs::{
         arithmetic::Field,
-        circuit::{Layouter, SimpleFloorPlanner, Value},
+        circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
         plonk::{
-            create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
-            ConstraintSystem, Error, Fixed, Selector,
+            create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Challenge, Circuit, Column,
+            ConstraintSystem, Error, Expression, FirstPhase, Fixed, SecondPhase, Selector,
+            VirtualCells,
         },
         poly::Rotation,
         poly::{
@@ -35,11 +36,12 @@ pub struct ShuffleChip<F: Field> {
 #[derive(Clone, Debug)]
 pub struct ShuffleConfig {
     pub input_0: Column<Advice>,
-    pub input_1: Column<Fixed>,
+    pub input_1: Column<Advice>,
     pub shuffle_0: Column<Advice>,
     pub shuffle_1: Column<Advice>,
     pub s_input: Selector,
     pub s_shuffle: Selector,
+    pub digest: DigestConfig,
 }
 
 impl<F: Field> ShuffleChip<F> {
@@ -53,7 +55,7 @@ impl<F: Field> ShuffleChip<F> {
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         input_0: Column<Advice>,
-        input_1: Column<Fixed>,
+        input_1: Column<Advice>,
         shuffle_0: Column<Advice>,
         shuffle_1: Column<Advice>,
     ) -> ShuffleConfig {
@@ -63,7 +65,7 @@ impl<F: Field> ShuffleChip<F> {
             let s_input = meta.query_selector(s_input);
             let s_shuffle = meta.query_selector(s_shuffle);
             let input_0 = meta.query_advice(input_0, Rotation::cur());
-            let input_1 = meta.query_fixed(input_1, Rotation::cur());
+            let input_1 = meta.query_advice(input_1, Rotation::cur());
             let shuffle_0 = meta.query_advice(shuffle_0, Rotation::cur());
             let shuffle_1 = meta.query_advice(shuffle_1, Rotation::cur());
             vec![
@@ -71,6 +73,8 @@ impl<F: Field> ShuffleChip<F> {
                 (s_input * input_1, s_shuffle * shuffle_1),
             ]
         });
+        let digest = DigestChip::configure(meta);
+
         ShuffleConfig {
             input_0,
             input_1,
@@ -78,8 +82,245 @@ impl<F: Field> ShuffleChip<F> {
             shuffle_1,
             s_input,
             s_shuffle,
+            digest,
         }
     }
+
+    /// Folds the previous step's output `z_i` together with this step's
+    /// witness (`terms`, e.g. the step's assigned witness cells) into a
+    /// single cell, so the result of a fold step is cryptographically bound
+    /// to both the prior state and the witness it processed. A thin wrapper
+    /// over [`DigestChip::fold_in`], which `synthesize_step` in `main.rs`
+    /// calls directly to derive `z_out`.
+    pub fn digest_step(
+        &self,
+        layouter: impl Layouter<F>,
+        z_i: &AssignedCell<F, F>,
+        terms: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        DigestChip::construct(self.config.digest.clone()).fold_in(layouter, z_i, terms)
+    }
+}
+
+/// Sponge state width for [`DigestChip`]: one rate element (`state[0]`,
+/// where terms are absorbed) plus two capacity elements.
+const POSEIDON_WIDTH: usize = 3;
+
+/// Full rounds run between absorbing one term and the next. Each round
+/// applies a round constant, an `x^5` S-box to every state element, and a
+/// fixed MDS mix, so by the time the next term is absorbed the state no
+/// longer resembles a sum of its inputs.
+const POSEIDON_ROUNDS: usize = 8;
+
+/// Config for [`DigestChip`]: a width-3 Poseidon-style sponge, with a
+/// dedicated `term_in` column used to absorb one cell per round (zero on
+/// pure-mixing rounds) and one fixed round-constant column per state slot.
+#[derive(Clone, Debug)]
+pub struct DigestConfig {
+    pub state: [Column<Advice>; POSEIDON_WIDTH],
+    pub term_in: Column<Advice>,
+    pub rc: [Column<Fixed>; POSEIDON_WIDTH],
+    pub q_round: Selector,
+}
+
+/// A small Poseidon-style sponge, used to derive `z_out` from `z_i` and a
+/// step's witness cells (see [`ShuffleChip::digest_step`]).
+///
+/// `z_i` and every term are absorbed one at a time (`state[0] += item`,
+/// copy-constrained to the cell it came from), each followed by
+/// `POSEIDON_ROUNDS` rounds of round-constant addition, an `x^5` S-box and
+/// an MDS mix. Unlike a running sum, permuting or substituting the absorbed
+/// items changes every output bit, so the result is actually bound to which
+/// cells were folded in, not just their sum.
+pub struct DigestChip<F: Field> {
+    config: DigestConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> DigestChip<F> {
+    pub fn construct(config: DigestConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Round constant for row `row` of the digest region, state slot `slot`: distinct
+    /// per (row, slot) so the permutation has no fixed points or trivial
+    /// symmetries between rounds or between state elements.
+    fn round_constant(row: usize, slot: usize) -> F {
+        F::from((row * POSEIDON_WIDTH + slot + 1) as u64)
+    }
+
+    /// The MDS mix: a small circulant matrix, applied after the S-box.
+    /// `two` carries the field constant `2` already lifted into `T`
+    /// (`Expression::Constant`/`Value::known`, depending on the caller),
+    /// since `T` itself is only required to support `+`/`*` with itself.
+    fn mds<T>(state: [T; POSEIDON_WIDTH], two: T) -> [T; POSEIDON_WIDTH]
+    where
+        T: Clone + std::ops::Add<Output = T> + std::ops::Mul<Output = T>,
+    {
+        let [a, b, c] = state;
+        [
+            a.clone() * two.clone() + b.clone() + c.clone(),
+            a.clone() + b.clone() * two.clone() + c.clone(),
+            a + b + c * two,
+        ]
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> DigestConfig {
+        let state = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        for column in state {
+            meta.enable_equality(column);
+        }
+        let term_in = meta.advice_column();
+        meta.enable_equality(term_in);
+        let rc = [
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+        ];
+        let q_round = meta.selector();
+
+        meta.create_gate("poseidon round", |meta| {
+            let q_round = meta.query_selector(q_round);
+            let term_in = meta.query_advice(term_in, Rotation::cur());
+
+            let pre: [Expression<F>; POSEIDON_WIDTH] = std::array::from_fn(|i| {
+                let state_i = meta.query_advice(state[i], Rotation::cur());
+                let rc_i = meta.query_fixed(rc[i], Rotation::cur());
+                let absorbed = if i == 0 {
+                    state_i + term_in.clone()
+                } else {
+                    state_i
+                };
+                absorbed + rc_i
+            });
+            let sboxed = pre.map(|v| v.clone() * v.clone() * v.clone() * v.clone() * v);
+            let mixed = Self::mds(sboxed, Expression::Constant(F::from(2)));
+
+            (0..POSEIDON_WIDTH)
+                .map(|i| {
+                    let next = meta.query_advice(state[i], Rotation::next());
+                    q_round.clone() * (mixed[i].clone() - next)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        DigestConfig {
+            state,
+            term_in,
+            rc,
+            q_round,
+        }
+    }
+
+    /// Runs `POSEIDON_ROUNDS` rounds starting at `region` row `*row`,
+    /// absorbing `item` into `state[0]` on the first round (copy-constrained
+    /// to `item`'s cell) and mixing with `term_in = 0` on the rest.
+    /// Advances `*row` past the rounds it used and returns the new state.
+    fn absorb_and_permute(
+        &self,
+        region: &mut Region<'_, F>,
+        row: &mut usize,
+        state: [AssignedCell<F, F>; POSEIDON_WIDTH],
+        item: &AssignedCell<F, F>,
+    ) -> Result<[AssignedCell<F, F>; POSEIDON_WIDTH], Error> {
+        let mut state = state;
+        for round in 0..POSEIDON_ROUNDS {
+            let term_value = if round == 0 {
+                item.value().copied()
+            } else {
+                Value::known(F::ZERO)
+            };
+            let term_cell =
+                region.assign_advice(|| "term_in", self.config.term_in, *row, || term_value)?;
+            if round == 0 {
+                region.constrain_equal(term_cell.cell(), item.cell())?;
+            }
+            for (slot, rc_col) in self.config.rc.iter().enumerate() {
+                region.assign_fixed(
+                    || "rc",
+                    *rc_col,
+                    *row,
+                    || Value::known(Self::round_constant(*row, slot)),
+                )?;
+            }
+            self.config.q_round.enable(region, *row)?;
+
+            let pre: [Value<F>; POSEIDON_WIDTH] = std::array::from_fn(|i| {
+                let absorbed = if i == 0 {
+                    state[0].value().copied() + term_cell.value().copied()
+                } else {
+                    state[i].value().copied()
+                };
+                absorbed + Value::known(Self::round_constant(*row, i))
+            });
+            let sboxed = pre.map(|v| v * v * v * v * v);
+            let mixed = Self::mds(sboxed, Value::known(F::from(2)));
+
+            let mut next_state = Vec::with_capacity(POSEIDON_WIDTH);
+            for (i, value) in mixed.into_iter().enumerate() {
+                next_state.push(region.assign_advice(
+                    || "state_next",
+                    self.config.state[i],
+                    *row + 1,
+                    || value,
+                )?);
+            }
+            state = next_state
+                .try_into()
+                .unwrap_or_else(|_| unreachable!("exactly POSEIDON_WIDTH state cells"));
+            *row += 1;
+        }
+        Ok(state)
+    }
+
+    /// Hashes `z_i` followed by every cell in `terms` into a single cell.
+    pub fn fold_in(
+        &self,
+        mut layouter: impl Layouter<F>,
+        z_i: &AssignedCell<F, F>,
+        terms: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "poseidon digest",
+            |mut region| {
+                let mut row = 0usize;
+                let mut state: [AssignedCell<F, F>; POSEIDON_WIDTH] = [
+                    region.assign_advice(
+                        || "state0",
+                        self.config.state[0],
+                        row,
+                        || Value::known(F::ZERO),
+                    )?,
+                    region.assign_advice(
+                        || "state1",
+                        self.config.state[1],
+                        row,
+                        || Value::known(F::ZERO),
+                    )?,
+                    region.assign_advice(
+                        || "state2",
+                        self.config.state[2],
+                        row,
+                        || Value::known(F::ZERO),
+                    )?,
+                ];
+
+                for item in std::iter::once(z_i).chain(terms.iter()) {
+                    state = self.absorb_and_permute(&mut region, &mut row, state, item)?;
+                }
+
+                let [digest, _, _] = state;
+                Ok(digest)
+            },
+        )
+    }
 }
 
 #[derive(Default)]
@@ -101,7 +342,7 @@ impl<F: Field> Circuit<F> for MyCircuit<F> {
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
         let input_0 = meta.advice_column();
-        let input_1 = meta.fixed_column();
+        let input_1 = meta.advice_column();
         let shuffle_0 = meta.advice_column();
         let shuffle_1 = meta.advice_column();
         ShuffleChip::configure(meta, input_0, input_1, shuffle_0, shuffle_1)
@@ -120,7 +361,7 @@ impl<F: Field> Circuit<F> for MyCircuit<F> {
                     self.input_0.iter().zip(self.input_1.iter()).enumerate()
                 {
                     region.assign_advice(|| "input_0", ch.config.input_0, i, || *input_0)?;
-                    region.assign_fixed(
+                    region.assign_advice(
                         || "input_1",
                         ch.config.input_1,
                         i,
@@ -189,3 +430,219 @@ where
 
     assert_eq!(accepted, expected);
 }
+
+/// A running-product shuffle argument over `W` advice columns and `H` rows.
+///
+/// Unlike [`ShuffleChip`], which delegates to halo2's native `meta.shuffle`,
+/// this chip builds the argument out of plain gates so its cost is explicit
+/// and it is not limited to a single advice/fixed pair. Two challenges
+/// (`theta`, `gamma`) compress each row of the "original" and "shuffled"
+/// tables into one field element, and a second-phase running-product
+/// column `z` checks that the compressed multisets match:
+///
+/// `z[0] = 1`, `z[i+1] = z[i] * (gamma + o_i) / (gamma + s_i)` for every `i`
+/// in `0..H`, and `z[H] = 1` — so every row's pair is folded into the
+/// product, not just the first `H-1`.
+pub struct GrandProductShuffleChip<F: Field, const W: usize> {
+    pub config: GrandProductShuffleConfig<W>,
+    _marker: PhantomData<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GrandProductShuffleConfig<const W: usize> {
+    pub original: [Column<Advice>; W],
+    pub shuffled: [Column<Advice>; W],
+    pub z: Column<Advice>,
+    pub theta: Challenge,
+    pub gamma: Challenge,
+    pub q_shuffle: Selector,
+    pub q_first: Selector,
+    pub q_last: Selector,
+}
+
+impl<F: Field, const W: usize> GrandProductShuffleChip<F, W> {
+    pub fn construct(config: GrandProductShuffleConfig<W>) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Compress a row of `W` advice queries into one expression:
+    /// `Σ_j theta^j * row[j]`.
+    fn compress(
+        theta: Expression<F>,
+        row: &[Column<Advice>; W],
+        meta: &mut VirtualCells<F>,
+    ) -> Expression<F> {
+        let mut power_of_theta = Expression::Constant(F::ONE);
+        row.iter()
+            .fold(Expression::Constant(F::ZERO), |acc, column| {
+                let term = power_of_theta.clone() * meta.query_advice(*column, Rotation::cur());
+                power_of_theta = power_of_theta.clone() * theta.clone();
+                acc + term
+            })
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        original: [Column<Advice>; W],
+        shuffled: [Column<Advice>; W],
+    ) -> GrandProductShuffleConfig<W> {
+        let theta = meta.challenge_usable_after(FirstPhase);
+        let gamma = meta.challenge_usable_after(FirstPhase);
+        let z = meta.advice_column_in(SecondPhase);
+
+        let q_shuffle = meta.complex_selector();
+        let q_first = meta.selector();
+        let q_last = meta.selector();
+
+        meta.create_gate("z[0] = 1", |meta| {
+            let q_first = meta.query_selector(q_first);
+            let z = meta.query_advice(z, Rotation::cur());
+            vec![q_first * (Expression::Constant(F::ONE) - z)]
+        });
+
+        // Checked at row H, one past the last shuffle row: with q_shuffle
+        // enabled on every row 0..H, z[H] is the product over *all* H rows,
+        // not just the first H-1.
+        meta.create_gate("z[H] = 1", |meta| {
+            let q_last = meta.query_selector(q_last);
+            let z = meta.query_advice(z, Rotation::cur());
+            vec![q_last * (Expression::Constant(F::ONE) - z)]
+        });
+
+        meta.create_gate("z[i] * (gamma + o_i) = z[i+1] * (gamma + s_i)", |meta| {
+            let q_shuffle = meta.query_selector(q_shuffle);
+            let theta = meta.query_challenge(theta);
+            let gamma = meta.query_challenge(gamma);
+
+            let o = Self::compress(theta.clone(), &original, meta);
+            let s = Self::compress(theta, &shuffled, meta);
+
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_next = meta.query_advice(z, Rotation::next());
+
+            vec![q_shuffle * (z_cur * (gamma.clone() + o) - z_next * (gamma + s))]
+        });
+
+        GrandProductShuffleConfig {
+            original,
+            shuffled,
+            z,
+            theta,
+            gamma,
+            q_shuffle,
+            q_first,
+            q_last,
+        }
+    }
+
+    /// Assigns `original`/`shuffled` and derives the running-product column
+    /// `z` from the squeezed `theta`/`gamma` challenges, inverting the `H`
+    /// denominators `gamma + s_i` with one batch inversion instead of `H`
+    /// single inversions.
+    ///
+    /// Returns the assigned `original`/`shuffled` cells, row by row, so
+    /// callers can reuse them downstream (e.g. to fold them into a digest).
+    #[allow(clippy::type_complexity)]
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        original: &[[Value<F>; W]],
+        shuffled: &[[Value<F>; W]],
+    ) -> Result<(Vec<[AssignedCell<F, F>; W]>, Vec<[AssignedCell<F, F>; W]>), Error> {
+        assert_eq!(original.len(), shuffled.len());
+        let h = original.len();
+
+        let theta = layouter.get_challenge(self.config.theta);
+        let gamma = layouter.get_challenge(self.config.gamma);
+
+        let compress_row = |row: &[Value<F>; W], theta: Value<F>| {
+            row.iter()
+                .enumerate()
+                .fold(Value::known(F::ZERO), |acc, (j, cell)| {
+                    acc + theta.map(|t| t.pow([j as u64])) * *cell
+                })
+        };
+
+        layouter.assign_region(
+            || "grand product shuffle",
+            |mut region| {
+                let mut original_cells = Vec::with_capacity(h);
+                for (i, row) in original.iter().enumerate() {
+                    let mut cells = Vec::with_capacity(W);
+                    for (column, value) in self.config.original.iter().zip(row.iter()) {
+                        cells.push(region.assign_advice(|| "original", *column, i, || *value)?);
+                    }
+                    original_cells.push(
+                        cells
+                            .try_into()
+                            .unwrap_or_else(|_| unreachable!("row has exactly W cells")),
+                    );
+                }
+                let mut shuffled_cells = Vec::with_capacity(h);
+                for (i, row) in shuffled.iter().enumerate() {
+                    let mut cells = Vec::with_capacity(W);
+                    for (column, value) in self.config.shuffled.iter().zip(row.iter()) {
+                        cells.push(region.assign_advice(|| "shuffled", *column, i, || *value)?);
+                    }
+                    shuffled_cells.push(
+                        cells
+                            .try_into()
+                            .unwrap_or_else(|_| unreachable!("row has exactly W cells")),
+                    );
+                    // Every row's pair must be multiplied into the product,
+                    // including the last one: leaving it out (as a bare
+                    // `i + 1 < h` guard would) lets a prover swap row H-1 of
+                    // `original`/`shuffled` for anything without tripping any
+                    // constraint.
+                    self.config.q_shuffle.enable(&mut region, i)?;
+                }
+                self.config.q_first.enable(&mut region, 0)?;
+                self.config.q_last.enable(&mut region, h)?;
+
+                // Compress every row, then invert all `H` denominators at
+                // once: `(Π_{k<=i} denom[k])^-1 * Π_{k<i} denom[k]` recovers
+                // `1 / denom[i]` after a single full-product inversion.
+                //
+                // `z` has H+1 rows: z[0] = 1, z[H] = 1, and z[i+1] folds in
+                // row i's pair for every i in 0..H, so all H rows are
+                // actually checked, not just the first H-1.
+                let mut z = vec![Value::known(F::ONE); h + 1];
+                let denominators: Vec<Value<F>> = shuffled
+                    .iter()
+                    .map(|row| gamma + compress_row(row, theta))
+                    .collect();
+                let numerators: Vec<Value<F>> = original
+                    .iter()
+                    .map(|row| gamma + compress_row(row, theta))
+                    .collect();
+
+                let mut running_products = Vec::with_capacity(h);
+                let mut acc = Value::known(F::ONE);
+                for d in &denominators {
+                    running_products.push(acc);
+                    acc = acc * *d;
+                }
+                let total_inv = acc.map(|acc| acc.invert().unwrap());
+                let mut acc_inv = total_inv;
+                let mut denom_inv = vec![Value::known(F::ZERO); h];
+                for idx in (0..h).rev() {
+                    denom_inv[idx] = acc_inv * running_products[idx];
+                    acc_inv = acc_inv * denominators[idx];
+                }
+
+                for i in 0..h {
+                    z[i + 1] = z[i] * numerators[i] * denom_inv[i];
+                }
+
+                for (i, value) in z.iter().enumerate() {
+                    region.assign_advice(|| "z", self.config.z, i, || *value)?;
+                }
+
+                Ok((original_cells, shuffled_cells))
+            },
+        )
+    }
+}